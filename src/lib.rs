@@ -0,0 +1,631 @@
+//! mandelbrod_rs のコアライブラリ。
+//! ファイルI/Oを行わない描画ロジック（escape_time, pixel_to_point, render系）と、
+//! CLIバイナリ・wasmエントリポイントの両方が共有する公開APIをここにまとめる。
+
+extern crate num;
+
+use num::Complex;
+
+/// 選択可能なフラクタルの種類。反復ステップだけが種類ごとに異なる。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FractalKind {
+    Mandelbrot,
+    Multibrot3,
+    BurningShip,
+}
+
+impl FractalKind {
+    /// z_{n+1} = step(z_n, c) を計算する、フラクタル固有の反復ステップ。
+    /// Mandelbrot: z = z^2 + c
+    /// Multibrot3: z = z^3 + c
+    /// BurningShip: z = (|re(z)| + |im(z)|i)^2 + c
+    fn step(self, z: Complex<f64>, c: Complex<f64>) -> Complex<f64> {
+        match self {
+            FractalKind::Mandelbrot => z * z + c,
+            FractalKind::Multibrot3 => z * z * z + c,
+            FractalKind::BurningShip => {
+                let folded = Complex {re: z.re.abs(), im: z.im.abs()};
+                folded * folded + c
+            }
+        }
+    }
+}
+
+impl FromStr for FractalKind {
+    type Err = String;
+
+    /// "mandelbrot" "mandelbrot3" "burning_ship" のいずれかを FractalKind に変換する。
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mandelbrot" => Ok(FractalKind::Mandelbrot),
+            "mandelbrot3" => Ok(FractalKind::Multibrot3),
+            "burning_ship" => Ok(FractalKind::BurningShip),
+            _ => Err(format!("unknown fractal kind: {}", s))
+        }
+    }
+}
+
+#[test]
+fn test_fractal_kind_from_str() {
+    assert_eq!("mandelbrot".parse(), Ok(FractalKind::Mandelbrot));
+    assert_eq!("mandelbrot3".parse(), Ok(FractalKind::Multibrot3));
+    assert_eq!("burning_ship".parse(), Ok(FractalKind::BurningShip));
+    assert!("".parse::<FractalKind>().is_err());
+}
+
+#[test]
+fn test_escape_time_mandelbrot() {
+    let c = Complex {re: 2.0, im: 0.0};
+    assert_eq!(escape_time(c, 20, FractalKind::Mandelbrot),
+                Some((1, Complex {re: 6.0, im: 0.0})));
+}
+
+#[test]
+fn test_escape_time_multibrot3() {
+    let c = Complex {re: 1.2, im: 0.0};
+    assert_eq!(escape_time(c, 20, FractalKind::Multibrot3),
+                Some((1, Complex {re: 2.928, im: 0.0})));
+}
+
+#[test]
+fn test_escape_time_burning_ship() {
+    // 同じcでもMandelbrotは脱出するのに対し、絶対値を取ってから二乗する
+    // BurningShipでは脱出しない点。foldが軌道を実際に変えていることを確認する。
+    let c = Complex {re: -1.0, im: -1.0};
+    assert_eq!(escape_time(c, 20, FractalKind::Mandelbrot),
+                Some((2, Complex {re: -1.0, im: -3.0})));
+    assert_eq!(escape_time(c, 20, FractalKind::BurningShip), None);
+}
+
+/// limit を繰り返し回数の上限として、c が kind の示すフラクタル集合に含まれるかを判定する
+/// c がフラクタル集合に含まれないならSome((i, z))を返却する。
+/// i は c が原点を中心とする半径2の円から出るまでにかかった繰り返し回数、
+/// z はそのとき脱出したあとの値で、滑らかな反復回数の計算に使う。
+///
+/// c がフラクタル集合に含まれているらしい場合
+///     （正確に言うと繰り返し回数の上限に達しても c がフラクタル集合に含まれていることを示せなかった場合)
+/// Noneを返却する。
+pub fn escape_time(c: Complex<f64>, limit: u32, kind: FractalKind) -> Option<(u32, Complex<f64>)> {
+    let mut z = Complex {re: 0.0, im: 0.0};
+    for i in 0..limit {
+        z = kind.step(z, c);
+        if z.norm_sqr() > 4.0 {
+            return Some((i, z))
+        }
+    }
+    None
+}
+
+#[allow(dead_code)]
+fn complex_square_add_loop(c: Complex<f64>) {
+    let mut z = Complex{re: 0.0, im: 0.0};
+    loop {
+        z = z * z + c;
+    }
+}
+
+use std::str::FromStr;
+
+/// 文字列 s は座標のペア。 "400x600" "1.0,0.5"など
+/// s は　<LEFT><SEP><RIGHT>の形でなければならない。
+/// <SEP>はsepalator引数で与えられる文字で<LEFT>と<RIGHT>はT::from_strでパースできる文字列
+/// s が適切な形であればSome(x, y)を返す。
+/// パースできなければNoneを返す。
+pub fn parse_pair<T: FromStr> (s: &str, sepalator: char) -> Option<(T, T)> {
+    match s.find(sepalator) {
+        None => None,
+        Some(index) => {
+            match (T::from_str(&s[..index]), T::from_str(&s[index + 1..])) {
+                (Ok(l), Ok(r)) => Some((l, r)),
+                _ => None
+            }
+        }
+    }
+}
+
+#[test]
+fn test_parse_pair() {
+    assert_eq!(parse_pair::<i32> ("", ','),         None);
+    assert_eq!(parse_pair::<i32> ("10,", ','),      None);
+    assert_eq!(parse_pair::<i32> (",10", ','),      None);
+    assert_eq!(parse_pair::<i32> ("10,20", ','),    Some((10, 20)));
+    assert_eq!(parse_pair::<i32> ("10,20xy", ','),  None);
+    assert_eq!(parse_pair::<f64> ("0.5x", 'x'), None);
+    assert_eq!(parse_pair::<f64> ("0.5x1.5", 'x'), Some((0.5, 1.5)));
+}
+
+/// カンマで分けられた浮動小数点数のペアをパースして複素数を返す。
+pub fn parse_complex(s: &str) -> Option<Complex<f64>> {
+    parse_pair(s, ',').map(|(re, im)| Complex {re, im})
+}
+
+#[test]
+fn test_parse_complex() {
+    assert_eq!(parse_complex("1.25,-0.0625"), Some(Complex {re: 1.25, im: -0.0625}))
+}
+
+/// 出力される画像のピクセルの位置を取り対応する複素数平面上の点を返す。
+/// bounds は出力画像の幅と高さをピクセル単位で与える。
+/// pixelは画面上の特定のピクセルを(行, 列)ペアの形で指定する。
+/// 仮引数upper_left, lower_rightは出力画像に描画する
+/// 複素平面を左上と右下で指定する。
+pub fn pixel_to_point(bounds: (usize, usize),
+                    pixel: (usize, usize),
+                    upper_left: Complex<f64>,
+                    lower_right: Complex<f64>) -> Complex<f64> {
+    let (width, height) = (lower_right.re - upper_left.re,
+                            upper_left.im - lower_right.im);
+    Complex {
+        re: upper_left.re + pixel.0 as f64 * width / bounds.0 as f64,
+        im: upper_left.im - pixel.1 as f64 * height / bounds.1 as f64
+    }
+}
+
+#[test]
+fn test_pixel_to_point() {
+    assert_eq!(pixel_to_point((100,100),
+                                (25, 75),
+                                Complex {re: -1.0, im: 1.0},
+                                Complex {re: 1.0, im: -1.0}),
+                Complex {re: -0.5, im: -0.5});
+}
+
+/// 矩形派にのフラクタル集合をピクセルのバッファに描画する。
+/// 仮引数 boundsはバッファpixelsの幅と高さを指定する。
+/// バッファpixelsはピクセルのグレースケールの値をバイトで保持する。
+/// upper_leftとlower_rightはピクセルバッファの左上と右下に対応する
+/// 複素平面上の点を指定する。kindで描画するフラクタルの種類、limitで反復回数の上限を指定する。
+pub fn render(pixels: &mut [u8],
+            bounds: (usize, usize),
+            upper_left: Complex<f64>,
+            lower_right: Complex<f64>,
+            kind: FractalKind,
+            limit: u32)
+{
+    assert!(pixels.len() == bounds.0 * bounds.1);
+    for row in 0 .. bounds.1 {
+        for column in 0 .. bounds.0 {
+            let point = pixel_to_point(bounds, (column, row),
+                                        upper_left, lower_right);
+            pixels[row * bounds.0 + column] =
+                match escape_time(point, limit, kind) {
+                    None => 0,
+                    Some((count, _)) => 255 - (count * 255 / limit) as u8
+                };
+        }
+    }
+}
+
+/// escape_time が返す (count, z) を、段階の目立たない小数の反復回数へ変換する。
+/// 標準化反復回数の式: mu = i + 1 - ln(ln(|z|)) / ln(2)
+fn smooth_iteration_count(count: u32, z: Complex<f64>) -> f64 {
+    let log_modulus = z.norm_sqr().ln() / 2.0;
+    count as f64 + 1.0 - (log_modulus.ln() / 2.0f64.ln())
+}
+
+#[test]
+fn test_smooth_iteration_count() {
+    let mu = smooth_iteration_count(5, Complex {re: 2.0, im: 0.0});
+    assert!((mu - 6.528766372944897).abs() < 1e-9);
+}
+
+/// 組み込みのグラデーションパレット。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Palette {
+    Blue,
+    Fire,
+}
+
+impl FromStr for Palette {
+    type Err = String;
+
+    /// "blue" "fire" のいずれかを Palette に変換する。
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "blue" => Ok(Palette::Blue),
+            "fire" => Ok(Palette::Fire),
+            _ => Err(format!("unknown palette: {}", s))
+        }
+    }
+}
+
+impl Palette {
+    /// 0.0から1.0に正規化された反復回数 t を RGB の3バイトへ写像する。
+    fn color_at(self, t: f64) -> [u8; 3] {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            // 青(0, 0, 255)から白へのなめらかなグラデーション
+            Palette::Blue => {
+                let v = (t * 255.0) as u8;
+                [v, v, 255]
+            }
+            // 黒→赤→橙→黄→白と進むクラシックな炎グラデーション
+            Palette::Fire => {
+                let r = (t * 3.0).min(1.0);
+                let g = (t * 3.0 - 1.0).clamp(0.0, 1.0);
+                let b = (t * 3.0 - 2.0).clamp(0.0, 1.0);
+                [(r * 255.0) as u8, (g * 255.0) as u8, (b * 255.0) as u8]
+            }
+        }
+    }
+}
+
+#[test]
+fn test_palette_color_at_blue() {
+    assert_eq!(Palette::Blue.color_at(0.0), [0, 0, 255]);
+    assert_eq!(Palette::Blue.color_at(0.5), [127, 127, 255]);
+    assert_eq!(Palette::Blue.color_at(1.0), [255, 255, 255]);
+}
+
+#[test]
+fn test_palette_color_at_fire() {
+    assert_eq!(Palette::Fire.color_at(0.0), [0, 0, 0]);
+    assert_eq!(Palette::Fire.color_at(0.5), [255, 127, 0]);
+    assert_eq!(Palette::Fire.color_at(1.0), [255, 255, 255]);
+}
+
+/// 矩形上のフラクタル集合をRGBのピクセルバッファに描画する。
+/// pixelsは1ピクセルあたり3バイト（R,G,B）を保持し、長さは bounds.0 * bounds.1 * 3 でなければならない。
+/// escape_timeの結果を smooth_iteration_count で小数化してから palette に通して色を求め、
+/// 集合の内側（escape_timeがNoneを返す点）は黒にする。
+pub fn render_color(pixels: &mut [u8],
+            bounds: (usize, usize),
+            upper_left: Complex<f64>,
+            lower_right: Complex<f64>,
+            kind: FractalKind,
+            palette: Palette,
+            limit: u32)
+{
+    assert!(pixels.len() == bounds.0 * bounds.1 * 3);
+    for row in 0 .. bounds.1 {
+        for column in 0 .. bounds.0 {
+            let point = pixel_to_point(bounds, (column, row),
+                                        upper_left, lower_right);
+            let color = match escape_time(point, limit, kind) {
+                None => [0, 0, 0],
+                Some((count, z)) => palette.color_at(smooth_iteration_count(count, z) / limit as f64)
+            };
+            let offset = (row * bounds.0 + column) * 3;
+            pixels[offset .. offset + 3].copy_from_slice(&color);
+        }
+    }
+}
+
+extern crate rayon;
+
+use rayon::prelude::*;
+use std::thread;
+
+/// render をバンド単位で並列に実行する。
+/// ピクセルバッファを rows_per_band * bounds.0 バイトごとの連続した
+/// バンドに分割し、各バンドの upper_left/lower_right を pixel_to_point で求めたうえで
+/// 既存の render に委譲する。バンド数（スレッド数）は available_parallelism から自動的に決まる。
+pub fn render_parallel(pixels: &mut [u8],
+            bounds: (usize, usize),
+            upper_left: Complex<f64>,
+            lower_right: Complex<f64>,
+            kind: FractalKind,
+            limit: u32)
+{
+    assert!(pixels.len() == bounds.0 * bounds.1);
+
+    let threads = thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1);
+    let rows_per_band = bounds.1 / threads + 1;
+
+    pixels.par_chunks_mut(rows_per_band * bounds.0).enumerate().for_each(|(i, band)| {
+        let top = rows_per_band * i;
+        let height = band.len() / bounds.0;
+        let band_bounds = (bounds.0, height);
+        let band_upper_left = pixel_to_point(bounds, (0, top), upper_left, lower_right);
+        let band_lower_right = pixel_to_point(bounds, (bounds.0, top + height),
+                                                upper_left, lower_right);
+        render(band, band_bounds, band_upper_left, band_lower_right, kind, limit);
+    });
+}
+
+#[test]
+fn test_render_parallel_matches_render() {
+    let bounds = (37, 29);
+    let upper_left = Complex {re: -1.2, im: 0.35};
+    let lower_right = Complex {re: -1.0, im: 0.20};
+
+    let mut sequential = vec![0; bounds.0 * bounds.1];
+    render(&mut sequential, bounds, upper_left, lower_right, FractalKind::Mandelbrot, 255);
+
+    let mut parallel = vec![0; bounds.0 * bounds.1];
+    render_parallel(&mut parallel, bounds, upper_left, lower_right, FractalKind::Mandelbrot, 255);
+
+    assert_eq!(sequential, parallel);
+}
+
+/// render_color をバンド単位で並列に実行する。分割の考え方は render_parallel と同じだが、
+/// 1ピクセルあたり3バイトになる分、バンドの境界もその分だけずらす。
+pub fn render_color_parallel(pixels: &mut [u8],
+            bounds: (usize, usize),
+            upper_left: Complex<f64>,
+            lower_right: Complex<f64>,
+            kind: FractalKind,
+            palette: Palette,
+            limit: u32)
+{
+    assert!(pixels.len() == bounds.0 * bounds.1 * 3);
+
+    let threads = thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1);
+    let rows_per_band = bounds.1 / threads + 1;
+
+    pixels.par_chunks_mut(rows_per_band * bounds.0 * 3).enumerate().for_each(|(i, band)| {
+        let top = rows_per_band * i;
+        let height = band.len() / (bounds.0 * 3);
+        let band_bounds = (bounds.0, height);
+        let band_upper_left = pixel_to_point(bounds, (0, top), upper_left, lower_right);
+        let band_lower_right = pixel_to_point(bounds, (bounds.0, top + height),
+                                                upper_left, lower_right);
+        render_color(band, band_bounds, band_upper_left, band_lower_right, kind, palette, limit);
+    });
+}
+
+extern crate rand;
+
+use rand::Rng;
+
+/// pixel_to_point の逆写像。複素平面上の点がviewport内にあれば対応するピクセル座標を返す。
+/// viewportの外側の点に対してはNoneを返す。
+pub fn point_to_pixel(bounds: (usize, usize),
+            point: Complex<f64>,
+            upper_left: Complex<f64>,
+            lower_right: Complex<f64>) -> Option<(usize, usize)> {
+    let (width, height) = (lower_right.re - upper_left.re,
+                            upper_left.im - lower_right.im);
+    let column = (point.re - upper_left.re) / width * bounds.0 as f64;
+    let row = (upper_left.im - point.im) / height * bounds.1 as f64;
+    if column < 0.0 || row < 0.0 {
+        return None;
+    }
+    let (column, row) = (column as usize, row as usize);
+    if column >= bounds.0 || row >= bounds.1 {
+        None
+    } else {
+        Some((column, row))
+    }
+}
+
+#[test]
+fn test_point_to_pixel() {
+    let bounds = (100, 100);
+    let upper_left = Complex {re: -1.0, im: 1.0};
+    let lower_right = Complex {re: 1.0, im: -1.0};
+
+    // pixel_to_pointの逆であることを確認する
+    assert_eq!(point_to_pixel(bounds, Complex {re: -0.5, im: -0.5}, upper_left, lower_right),
+                Some((25, 75)));
+
+    // upper_leftより外側（負のcolumn/row）はNone
+    assert_eq!(point_to_pixel(bounds, Complex {re: -2.0, im: 1.0}, upper_left, lower_right), None);
+    assert_eq!(point_to_pixel(bounds, Complex {re: -1.0, im: 2.0}, upper_left, lower_right), None);
+
+    // lower_right自体はviewportの半開区間の外（column/row == bounds）でNone
+    assert_eq!(point_to_pixel(bounds, lower_right, upper_left, lower_right), None);
+}
+
+/// Buddhabrotのヒットカウントを蓄積するバッファ。
+/// シャードに分割して独立に埋め、あとでmergeできるようにcountsをそのまま持つ。
+pub struct AccumulationBuffer {
+    bounds: (usize, usize),
+    counts: Vec<u32>,
+}
+
+impl AccumulationBuffer {
+    pub fn new(bounds: (usize, usize)) -> AccumulationBuffer {
+        AccumulationBuffer {bounds, counts: vec![0; bounds.0 * bounds.1]}
+    }
+
+    fn hit(&mut self, pixel: (usize, usize)) {
+        self.counts[pixel.1 * self.bounds.0 + pixel.0] += 1;
+    }
+
+    /// otherのヒットカウントを自分へ合算する。
+    pub fn merge(mut self, other: &AccumulationBuffer) -> AccumulationBuffer {
+        for (count, other_count) in self.counts.iter_mut().zip(&other.counts) {
+            *count += other_count;
+        }
+        self
+    }
+
+    /// ヒットカウントを0..255に正規化したグレースケールのピクセルバッファへ変換する。
+    pub fn normalize(&self) -> Vec<u8> {
+        let max = self.counts.iter().cloned().max().unwrap_or(0).max(1);
+        self.counts.iter()
+            .map(|&count| (count as f64 / max as f64 * 255.0) as u8)
+            .collect()
+    }
+}
+
+/// Buddhabrotをサンプリングし、ヒットカウントをbufferへ蓄積する。
+/// viewport内に一様乱数で点cを取り、z = z*z + c をlimit回まで反復する。
+/// 脱出した軌道だけを、通過した各ピクセルのカウントとして加算する
+/// （脱出しない、つまり集合に含まれるらしい点の軌道は捨てる）。
+pub fn accumulate_buddhabrot(buffer: &mut AccumulationBuffer,
+            bounds: (usize, usize),
+            upper_left: Complex<f64>,
+            lower_right: Complex<f64>,
+            limit: u32,
+            samples: u64)
+{
+    let mut rng = rand::thread_rng();
+    let mut trajectory = Vec::with_capacity(limit as usize);
+
+    for _ in 0..samples {
+        let c = Complex {
+            re: rng.gen_range(upper_left.re, lower_right.re),
+            im: rng.gen_range(lower_right.im, upper_left.im),
+        };
+
+        let mut z = Complex {re: 0.0, im: 0.0};
+        trajectory.clear();
+        let mut escaped = false;
+        for _ in 0..limit {
+            z = FractalKind::Mandelbrot.step(z, c);
+            trajectory.push(z);
+            if z.norm_sqr() > 4.0 {
+                escaped = true;
+                break;
+            }
+        }
+
+        if escaped {
+            for &point in &trajectory {
+                if let Some(pixel) = point_to_pixel(bounds, point, upper_left, lower_right) {
+                    buffer.hit(pixel);
+                }
+            }
+        }
+    }
+}
+
+/// accumulate_buddhabrotをシャードに分割して並列に実行し、結果を1つのバッファへ合算する。
+pub fn render_buddhabrot_parallel(bounds: (usize, usize),
+            upper_left: Complex<f64>,
+            lower_right: Complex<f64>,
+            limit: u32,
+            samples: u64) -> AccumulationBuffer {
+    let threads = thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1);
+    let samples_per_shard = samples / threads as u64;
+    let remainder = samples % threads as u64;
+
+    (0 .. threads)
+        .into_par_iter()
+        .map(|i| {
+            let shard_samples = samples_per_shard + if (i as u64) < remainder { 1 } else { 0 };
+            let mut shard = AccumulationBuffer::new(bounds);
+            accumulate_buddhabrot(&mut shard, bounds, upper_left, lower_right,
+                                    limit, shard_samples);
+            shard
+        })
+        .reduce(|| AccumulationBuffer::new(bounds), |a, b| a.merge(&b))
+}
+
+extern crate image;
+
+use image::ColorType;
+use image::png::PNGEncoder;
+use image::jpeg::JPEGEncoder;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+/// filenameの拡張子からフォーマットを選び、ピクセルバッファを画像として書き出す。
+/// pnm/ppm/pgmはwrite_pnmへ委譲し、それ以外（デフォルトはpng）はimageクレートの
+/// エンコーダに任せる。
+pub fn write_image(filename: &str, pixels: &[u8], bounds: (usize, usize), color_type: ColorType)
+    -> Result<(), std::io::Error>
+{
+    let extension = Path::new(filename)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+
+    match extension.as_str() {
+        "pnm" | "ppm" | "pgm" => write_pnm(filename, pixels, bounds, color_type),
+        "jpg" | "jpeg" => {
+            let mut output = File::create(filename)?;
+            let mut encoder = JPEGEncoder::new(&mut output);
+            encoder.encode(pixels, bounds.0 as u32, bounds.1 as u32, color_type)?;
+            Ok(())
+        }
+        _ => {
+            let output = File::create(filename)?;
+            let encoder = PNGEncoder::new(output);
+            encoder.encode(pixels,
+                            bounds.0 as u32, bounds.1 as u32,
+                            color_type)?;
+            Ok(())
+        }
+    }
+}
+
+/// PNM形式（グレースケールはP5、RGBはP6）でASCIIヘッダに続けて生のピクセルバイト列を書き出す。
+/// imageクレートのエンコーダを経由しない自己完結した実装で、他のツールへパイプしたい場合や
+/// 重いエンコーダが使えない環境向け。
+fn write_pnm(filename: &str, pixels: &[u8], bounds: (usize, usize), color_type: ColorType)
+    -> Result<(), std::io::Error>
+{
+    let mut output = File::create(filename)?;
+    let magic = match color_type {
+        ColorType::RGB(8) => "P6",
+        _ => "P5",
+    };
+    write!(output, "{}\n{} {}\n255\n", magic, bounds.0, bounds.1)?;
+    output.write_all(pixels)?;
+    Ok(())
+}
+
+#[test]
+fn test_write_pnm() {
+    let bounds = (3, 2);
+
+    let path = std::env::temp_dir().join("mandelbrod_rs_test_write_pnm_gray.pgm");
+    let pixels = vec![0u8; bounds.0 * bounds.1];
+    write_pnm(path.to_str().unwrap(), &pixels, bounds, ColorType::Gray(8)).unwrap();
+    let bytes = std::fs::read(&path).unwrap();
+    assert!(bytes.starts_with(b"P5\n3 2\n255\n"));
+    assert_eq!(bytes.len() - b"P5\n3 2\n255\n".len(), bounds.0 * bounds.1);
+    std::fs::remove_file(&path).unwrap();
+
+    let path = std::env::temp_dir().join("mandelbrod_rs_test_write_pnm_rgb.ppm");
+    let pixels = vec![0u8; bounds.0 * bounds.1 * 3];
+    write_pnm(path.to_str().unwrap(), &pixels, bounds, ColorType::RGB(8)).unwrap();
+    let bytes = std::fs::read(&path).unwrap();
+    assert!(bytes.starts_with(b"P6\n3 2\n255\n"));
+    assert_eq!(bytes.len() - b"P6\n3 2\n255\n".len(), bounds.0 * bounds.1 * 3);
+    std::fs::remove_file(&path).unwrap();
+}
+
+/// ファイルI/Oを持たない最小限の公開API。wasmエントリポイントから使われる
+/// （CLIはこの関数を経由せず、render系の関数を直接呼ぶ）。
+/// 幅・高さ・左上/右下の複素数の実部虚部・反復回数の上限を受け取り、
+/// グレースケール（1ピクセル1バイト）のピクセルバッファを描画して返す。
+///
+/// wasm32ターゲットには`std::thread`による実OSスレッドが無く、
+/// wasm-bindgen-rayon等の特別なビルド設定（SharedArrayBuffer + atomics）なしでは
+/// rayonのスレッドプール初期化が実行時にパニックするため、ここでは意図的に
+/// 逐次版のrenderを使う。
+pub fn render_mandelbrot(width: usize, height: usize,
+            upper_left_re: f64, upper_left_im: f64,
+            lower_right_re: f64, lower_right_im: f64,
+            limit: u32) -> Vec<u8>
+{
+    let bounds = (width, height);
+    let upper_left = Complex {re: upper_left_re, im: upper_left_im};
+    let lower_right = Complex {re: lower_right_re, im: lower_right_im};
+
+    let mut pixels = vec![0; bounds.0 * bounds.1];
+    render(&mut pixels, bounds, upper_left, lower_right, FractalKind::Mandelbrot, limit);
+    pixels
+}
+
+#[cfg(target_arch = "wasm32")]
+mod wasm {
+    extern crate wasm_bindgen;
+
+    use wasm_bindgen::prelude::*;
+
+    /// ブラウザのcanvasへ直接描画できるよう、render_mandelbrotを薄くラップしたエントリポイント。
+    #[wasm_bindgen]
+    pub fn render_mandelbrot(width: usize, height: usize,
+                upper_left_re: f64, upper_left_im: f64,
+                lower_right_re: f64, lower_right_im: f64,
+                limit: u32) -> Vec<u8>
+    {
+        super::render_mandelbrot(width, height,
+                                    upper_left_re, upper_left_im,
+                                    lower_right_re, lower_right_im,
+                                    limit)
+    }
+}